@@ -0,0 +1,185 @@
+use serde::Deserialize;
+use std::env;
+
+const CONFIG_PATH: &str = "absolutelyright.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Sqlite
+    }
+}
+
+/// Service configuration, loaded from `absolutelyright.toml` with
+/// environment variables taking precedence over file values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub data_dir: String,
+    pub listen_addr: String,
+    pub secret: Option<String>,
+    pub backend: Backend,
+    pub postgres_uri: Option<String>,
+    pub allowed_patterns: Option<Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Preserve the old heuristic as the file-less, env-less fallback:
+        // /app/data on Fly.io, the working directory otherwise.
+        let data_dir = if std::path::Path::new("/app/data").exists() {
+            "/app/data"
+        } else {
+            "."
+        };
+
+        Self {
+            data_dir: data_dir.to_string(),
+            listen_addr: "0.0.0.0:3003".to_string(),
+            secret: None,
+            backend: Backend::Sqlite,
+            postgres_uri: None,
+            allowed_patterns: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Config> {
+        let mut config = match std::fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => toml::from_str(&contents)?,
+            Err(_) => Config::default(),
+        };
+
+        config.apply_env_overrides(|key| env::var(key).ok())?;
+        Ok(config)
+    }
+
+    /// Applies `ABSOLUTELYRIGHT_*` overrides on top of file/default values.
+    /// Takes the var lookup as a closure so tests can exercise precedence
+    /// without mutating real process environment variables.
+    fn apply_env_overrides(
+        &mut self,
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<()> {
+        if let Some(val) = get_env("ABSOLUTELYRIGHT_DATA_DIR") {
+            self.data_dir = val;
+        }
+        if let Some(val) = get_env("ABSOLUTELYRIGHT_LISTEN_ADDR") {
+            self.listen_addr = val;
+        }
+        if let Some(val) = get_env("ABSOLUTELYRIGHT_SECRET") {
+            self.secret = Some(val);
+        }
+        if let Some(val) = get_env("ABSOLUTELYRIGHT_POSTGRES_URI") {
+            self.postgres_uri = Some(val);
+            self.backend = Backend::Postgres;
+        }
+        if let Some(val) = get_env("ABSOLUTELYRIGHT_BACKEND") {
+            self.backend = parse_backend(&val)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `backend` value from config/env, rejecting anything unrecognized
+/// instead of silently falling back to SQLite — a typo'd value should fail
+/// startup, not quietly point a deployment at the wrong store.
+fn parse_backend(value: &str) -> anyhow::Result<Backend> {
+    match value {
+        "sqlite" => Ok(Backend::Sqlite),
+        "postgres" => Ok(Backend::Postgres),
+        other => Err(anyhow::anyhow!(
+            "unrecognized backend {other:?} (expected \"sqlite\" or \"postgres\")"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_unchanged_with_no_env_vars() {
+        let mut config = Config::default();
+        let before = config.data_dir.clone();
+
+        config.apply_env_overrides(|_| None).unwrap();
+
+        assert_eq!(config.data_dir, before);
+        assert_eq!(config.listen_addr, "0.0.0.0:3003");
+        assert_eq!(config.backend, Backend::Sqlite);
+        assert!(config.secret.is_none());
+    }
+
+    #[test]
+    fn env_vars_override_file_values() {
+        let mut config: Config = toml::from_str(
+            r#"
+            data_dir = "/from/file"
+            listen_addr = "127.0.0.1:9000"
+            "#,
+        )
+        .unwrap();
+
+        config
+            .apply_env_overrides(|key| match key {
+                "ABSOLUTELYRIGHT_DATA_DIR" => Some("/from/env".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.data_dir, "/from/env");
+        // Untouched by the override closure, so the file value survives.
+        assert_eq!(config.listen_addr, "127.0.0.1:9000");
+    }
+
+    #[test]
+    fn postgres_uri_override_also_switches_backend() {
+        let mut config = Config::default();
+
+        config
+            .apply_env_overrides(|key| match key {
+                "ABSOLUTELYRIGHT_POSTGRES_URI" => Some("postgres://localhost/db".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.backend, Backend::Postgres);
+        assert_eq!(config.postgres_uri.as_deref(), Some("postgres://localhost/db"));
+    }
+
+    #[test]
+    fn backend_override_accepts_known_values() {
+        let mut config = Config::default();
+
+        config
+            .apply_env_overrides(|key| match key {
+                "ABSOLUTELYRIGHT_BACKEND" => Some("postgres".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.backend, Backend::Postgres);
+    }
+
+    #[test]
+    fn backend_override_rejects_unknown_values() {
+        let mut config = Config::default();
+
+        let result = config.apply_env_overrides(|key| match key {
+            "ABSOLUTELYRIGHT_BACKEND" => Some("typo-postgres".to_string()),
+            _ => None,
+        });
+
+        assert!(result.is_err());
+        // An invalid override must not silently leave the backend changed.
+        assert_eq!(config.backend, Backend::Sqlite);
+    }
+}
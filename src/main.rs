@@ -1,3 +1,8 @@
+mod auth;
+mod config;
+mod metrics;
+mod store;
+
 use axum::{
     http::{header, HeaderValue, Request},
     middleware,
@@ -5,92 +10,74 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use chrono::Utc;
-use serde::{Deserialize, Serialize};
-use std::env;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
-use tokio_rusqlite::Connection;
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
 use tower_http::services::ServeDir;
 use tower_http::set_header::SetResponseHeaderLayer;
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct DayCount {
-    day: String,
-    #[serde(flatten)]
-    patterns: HashMap<String, u32>,
-    total_messages: u32,
-}
-
-// Legacy struct for migration
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct DayCountLegacy {
-    day: String,
-    count: u32,
-    right_count: u32,
-    total_messages: u32,
+use tower_http::trace::TraceLayer;
+use tracing_subscriber::EnvFilter;
+
+use config::{Backend, Config};
+use metrics::Metrics;
+use store::{DayCount, PostgresStore, SqliteStore, Store};
+
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) store: Arc<dyn Store>,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) config: Arc<Config>,
 }
 
 #[tokio::main]
 async fn main() {
-    // Initialize SQLite database - use /app/data on Fly.io, local file otherwise
-    let db_path = if std::path::Path::new("/app/data").exists() {
-        "/app/data/counts.db"
-    } else {
-        "counts.db"
-    };
-    let db = Connection::open(db_path).await.unwrap();
-
-    // Create table if it doesn't exist
-    db.call(|conn| {
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS day_counts (
-                day TEXT PRIMARY KEY,
-                patterns TEXT NOT NULL DEFAULT '{}',
-                total_messages INTEGER DEFAULT 0
-            )",
-            [],
-        )?;
-
-        // Migration: Add patterns column if it doesn't exist
-        let has_patterns = conn
-            .prepare("SELECT patterns FROM day_counts LIMIT 1")
-            .is_ok();
-
-        if !has_patterns {
-            // Old schema - migrate data
-            println!("Migrating to new schema with dynamic patterns...");
-            let _ = conn.execute(
-                "ALTER TABLE day_counts ADD COLUMN patterns TEXT DEFAULT '{}'",
-                [],
-            );
-
-            // Migrate existing count and right_count to JSON
-            conn.execute(
-                r#"UPDATE day_counts
-                   SET patterns = json_object(
-                       'absolutely', COALESCE(count, 0),
-                       'right', COALESCE(right_count, 0)
-                   )"#,
-                [],
-            )?;
-
-            println!("Migration complete!");
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
+    let config = Config::load().unwrap();
+
+    // Select a backend per the config: Postgres when configured, SQLite
+    // (under `data_dir`) otherwise.
+    let store: Arc<dyn Store> = match config.backend {
+        Backend::Postgres => {
+            let postgres_uri = config
+                .postgres_uri
+                .as_deref()
+                .expect("backend = \"postgres\" requires postgres_uri");
+            Arc::new(PostgresStore::connect(postgres_uri).await.unwrap())
         }
+        Backend::Sqlite => {
+            let db_path = format!("{}/counts.db", config.data_dir);
+            Arc::new(SqliteStore::open(&db_path).await.unwrap())
+        }
+    };
 
-        Ok(())
-    })
-    .await
-    .unwrap();
-
-    let db = Arc::new(db);
+    let listen_addr = config.listen_addr.clone();
+    let state = AppState {
+        store,
+        metrics: Arc::new(Metrics::new()),
+        config: Arc::new(config),
+    };
 
     // Build router
     let app = Router::new()
         .route("/api/today", get(get_today))
         .route("/api/history", get(get_history))
-        .route("/api/set", post(set_day))
+        .route(
+            "/api/set",
+            post(set_day).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::verify_signature,
+            )),
+        )
+        .route(
+            "/api/increment",
+            post(increment_day).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::verify_signature,
+            )),
+        )
+        .route("/metrics", get(get_metrics))
         // Serve static files from ./frontend with cache control headers
         .nest_service(
             "/",
@@ -108,43 +95,24 @@ async fn main() {
             header::EXPIRES,
             HeaderValue::from_static("0"),
         ))
-        .layer(middleware::from_fn(log_pageview))
-        .with_state(db);
+        .layer(middleware::from_fn_with_state(state.clone(), track_request))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3003));
-    println!("listening on http://{addr}");
+    let addr: std::net::SocketAddr = listen_addr.parse().expect("invalid listen_addr");
+    tracing::info!(%addr, "listening");
     axum::serve(tokio::net::TcpListener::bind(addr).await.unwrap(), app)
         .await
         .unwrap();
 }
 
 async fn get_today(
-    state: axum::extract::State<Arc<Connection>>,
+    state: axum::extract::State<AppState>,
 ) -> (
     [(header::HeaderName, HeaderValue); 1],
     Json<HashMap<String, u32>>,
 ) {
-    let today = Utc::now().format("%Y-%m-%d").to_string();
-
-    let (patterns_json, total_messages) = state
-        .call(move |conn| {
-            let mut stmt =
-                conn.prepare("SELECT patterns, total_messages FROM day_counts WHERE day = ?1")?;
-            let result = stmt
-                .query_row([&today], |row| {
-                    Ok((
-                        row.get::<_, String>(0).unwrap_or_else(|_| "{}".to_string()),
-                        row.get::<_, u32>(1).unwrap_or(0)
-                    ))
-                })
-                .unwrap_or(("{}".to_string(), 0));
-            Ok(result)
-        })
-        .await
-        .unwrap();
-
-    let mut map: HashMap<String, u32> = serde_json::from_str(&patterns_json).unwrap_or_default();
-    map.insert("total_messages".to_string(), total_messages);
+    let map = state.store.today().await.unwrap();
 
     // Cache for 1 minutes
     (
@@ -157,31 +125,9 @@ async fn get_today(
 }
 
 async fn get_history(
-    state: axum::extract::State<Arc<Connection>>,
+    state: axum::extract::State<AppState>,
 ) -> ([(header::HeaderName, HeaderValue); 1], Json<Vec<DayCount>>) {
-    let history = state
-        .call(|conn| {
-            let mut stmt =
-                conn.prepare("SELECT day, patterns, total_messages FROM day_counts ORDER BY day")?;
-            let days = stmt
-                .query_map([], |row| {
-                    let day: String = row.get(0)?;
-                    let patterns_json: String = row.get::<_, String>(1).unwrap_or_else(|_| "{}".to_string());
-                    let total_messages: u32 = row.get(2).unwrap_or(0);
-
-                    let patterns: HashMap<String, u32> = serde_json::from_str(&patterns_json).unwrap_or_default();
-
-                    Ok(DayCount {
-                        day,
-                        patterns,
-                        total_messages,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-            Ok(days)
-        })
-        .await
-        .unwrap();
+    let history = state.store.history().await.unwrap();
 
     // Cache for 5 minutes
     (
@@ -193,6 +139,10 @@ async fn get_history(
     )
 }
 
+async fn get_metrics(state: axum::extract::State<AppState>) -> String {
+    state.metrics.render()
+}
+
 #[derive(Deserialize)]
 struct SetRequest {
     day: String,
@@ -203,26 +153,18 @@ struct SetRequest {
     #[serde(flatten)]
     patterns: HashMap<String, serde_json::Value>,
     total_messages: Option<u32>,
-    secret: Option<String>,
 }
 
+/// Bulk overwrite of a day's counts. Prefer `/api/increment` for live
+/// tallying: two concurrent `set_day` calls clobber each other's counts,
+/// since each does a full read-modify-write of the `patterns` blob on the
+/// client side.
 async fn set_day(
-    state: axum::extract::State<Arc<Connection>>,
+    state: axum::extract::State<AppState>,
     Json(payload): Json<SetRequest>,
 ) -> Result<Json<&'static str>, (axum::http::StatusCode, &'static str)> {
-    // Check secret if ABSOLUTELYRIGHT_SECRET is set
-    if let Ok(expected_secret) = env::var("ABSOLUTELYRIGHT_SECRET") {
-        match payload.secret {
-            Some(ref provided_secret) if provided_secret == &expected_secret => {
-                // Secret matches, continue
-            }
-            _ => {
-                // No secret provided or wrong secret
-                return Err((axum::http::StatusCode::UNAUTHORIZED, "Invalid secret"));
-            }
-        }
-    }
-    // If ABSOLUTELYRIGHT_SECRET is not set, allow access (for local dev)
+    // Authentication happens in the `verify_signature` middleware, which
+    // checks an HMAC over the raw body before this handler ever runs.
 
     // Build patterns map - support both old and new formats
     let mut patterns_map: HashMap<String, u32> = HashMap::new();
@@ -247,50 +189,92 @@ async fn set_day(
         }
     }
 
-    let patterns_json = serde_json::to_string(&patterns_map).unwrap();
+    // If an allowlist is configured, drop anything not on it
+    if let Some(allowed) = &state.config.allowed_patterns {
+        patterns_map.retain(|pattern, _| allowed.contains(pattern));
+    }
+
     let total_messages = payload.total_messages.unwrap_or(0);
 
     state
-        .call(move |conn| {
-            conn.execute(
-                "INSERT INTO day_counts (day, patterns, total_messages) VALUES (?1, ?2, ?3)
-                 ON CONFLICT(day) DO UPDATE SET patterns = ?2, total_messages = ?3",
-                [
-                    &payload.day,
-                    &patterns_json,
-                    &total_messages.to_string(),
-                ],
-            )?;
-            Ok(())
-        })
+        .store
+        .set(payload.day, patterns_map.clone(), total_messages)
         .await
         .unwrap();
 
+    state
+        .metrics
+        .record_set_day(&patterns_map, state.config.allowed_patterns.as_deref());
+
     Ok(Json("ok"))
 }
 
-async fn log_pageview(
+#[derive(Deserialize)]
+struct IncrementRequest {
+    day: String,
+    pattern: String,
+    by: u32,
+    #[serde(default)]
+    total_messages_delta: u32,
+}
+
+/// Atomically bumps a single pattern's count for a day, so concurrent
+/// reporters can't lose updates to a racing read-modify-write. This is the
+/// preferred path for live tallying; use `/api/set` only for bulk overwrites.
+async fn increment_day(
+    state: axum::extract::State<AppState>,
+    Json(payload): Json<IncrementRequest>,
+) -> Result<Json<&'static str>, (axum::http::StatusCode, &'static str)> {
+    if let Some(allowed) = &state.config.allowed_patterns {
+        if !allowed.contains(&payload.pattern) {
+            return Err((axum::http::StatusCode::BAD_REQUEST, "Pattern not allowed"));
+        }
+    }
+
+    state
+        .store
+        .increment(
+            payload.day,
+            payload.pattern.clone(),
+            payload.by,
+            payload.total_messages_delta,
+        )
+        .await
+        .unwrap();
+
+    state
+        .metrics
+        .record_pattern_totals(
+            &HashMap::from([(payload.pattern, payload.by)]),
+            state.config.allowed_patterns.as_deref(),
+        );
+
+    Ok(Json("ok"))
+}
+
+/// Updates the Prometheus counters exposed at `/metrics`: a pageview count
+/// for the main page, and a per-route request count for every request that
+/// matched a route (the main page included; unmatched requests are not counted).
+///
+/// Per-route counts are keyed by the matched route *template* (e.g.
+/// `/api/today`), not the raw request path — the path is attacker
+/// controlled, so keying on it would let any client grow the counter map
+/// without bound just by requesting many distinct (possibly nonexistent)
+/// paths.
+async fn track_request(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    matched_path: Option<axum::extract::MatchedPath>,
     req: Request<axum::body::Body>,
     next: middleware::Next,
 ) -> Response<axum::body::Body> {
     let path = req.uri().path().to_string();
     let method = req.method().to_string();
 
-    // Only log GET requests to main page
     if method == "GET" && (path == "/" || path == "/index.html") {
-        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let log_entry = format!("{timestamp} - Pageview: {path}\n");
-
-        // Append to log file - use /app/data on Fly.io, local file otherwise
-        let log_path = if std::path::Path::new("/app/data").exists() {
-            "/app/data/pageviews.log"
-        } else {
-            "pageviews.log"
-        };
-
-        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log_path) {
-            let _ = file.write_all(log_entry.as_bytes());
-        }
+        state.metrics.record_pageview();
+    }
+    if let Some(matched_path) = &matched_path {
+        state.metrics.record_request(matched_path.as_str());
     }
 
     next.run(req).await
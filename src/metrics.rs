@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Hard cap on distinct pattern labels tracked when no `allowed_patterns`
+/// allowlist is configured. Patterns are caller-supplied (via `/api/set`
+/// and `/api/increment`), so without this an unconfigured deployment would
+/// let any client grow `pattern_totals` without bound.
+const MAX_TRACKED_PATTERNS: usize = 1000;
+
+/// In-process Prometheus-style counters, scraped by the `/metrics` route.
+#[derive(Default)]
+pub struct Metrics {
+    pageviews_total: AtomicU64,
+    set_day_writes_total: AtomicU64,
+    requests_by_route: Mutex<HashMap<String, u64>>,
+    pattern_totals: Mutex<HashMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_pageview(&self) {
+        self.pageviews_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_request(&self, route: &str) {
+        let mut by_route = self.requests_by_route.lock().unwrap();
+        *by_route.entry(route.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_set_day(&self, patterns: &HashMap<String, u32>, allowed_patterns: Option<&[String]>) {
+        self.set_day_writes_total.fetch_add(1, Ordering::Relaxed);
+        self.record_pattern_totals(patterns, allowed_patterns);
+    }
+
+    /// Bounds growth of `pattern_totals` to `allowed_patterns` when an
+    /// allowlist is configured (unknown patterns are dropped, same as the
+    /// store write); otherwise caps distinct patterns at
+    /// `MAX_TRACKED_PATTERNS` so an unconfigured deployment can't have this
+    /// map grown without bound by arbitrary caller-supplied pattern names.
+    pub fn record_pattern_totals(&self, patterns: &HashMap<String, u32>, allowed_patterns: Option<&[String]>) {
+        let mut totals = self.pattern_totals.lock().unwrap();
+        for (pattern, count) in patterns {
+            if let Some(allowed) = allowed_patterns {
+                if !allowed.iter().any(|p| p == pattern) {
+                    continue;
+                }
+            } else if !totals.contains_key(pattern) && totals.len() >= MAX_TRACKED_PATTERNS {
+                continue;
+            }
+            *totals.entry(pattern.clone()).or_insert(0) += *count as u64;
+        }
+    }
+
+    /// Renders all counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP absolutelyright_pageviews_total Total pageviews of the main page.");
+        let _ = writeln!(out, "# TYPE absolutelyright_pageviews_total counter");
+        let _ = writeln!(
+            out,
+            "absolutelyright_pageviews_total {}",
+            self.pageviews_total.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(out, "# HELP absolutelyright_set_day_writes_total Total writes via /api/set.");
+        let _ = writeln!(out, "# TYPE absolutelyright_set_day_writes_total counter");
+        let _ = writeln!(
+            out,
+            "absolutelyright_set_day_writes_total {}",
+            self.set_day_writes_total.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(out, "# HELP absolutelyright_requests_total Total requests per route.");
+        let _ = writeln!(out, "# TYPE absolutelyright_requests_total counter");
+        for (route, count) in self.requests_by_route.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "absolutelyright_requests_total{{route=\"{}\"}} {count}",
+                escape_label_value(route)
+            );
+        }
+
+        let _ = writeln!(out, "# HELP absolutelyright_pattern_total Cumulative count per pattern.");
+        let _ = writeln!(out, "# TYPE absolutelyright_pattern_total counter");
+        for (pattern, count) in self.pattern_totals.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "absolutelyright_pattern_total{{pattern=\"{}\"}} {count}",
+                escape_label_value(pattern)
+            );
+        }
+
+        out
+    }
+}
+
+/// Escapes a label value per the Prometheus text exposition format, so a
+/// route or pattern name containing `"`, `\`, or a newline can't corrupt
+/// the output or forge extra metric lines.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_escapes_label_values() {
+        let metrics = Metrics::new();
+        // A route containing a quote, a backslash, and a newline must not
+        // be able to break out of the label value or inject extra lines.
+        metrics.record_request("/weird\"\\\npath");
+
+        let rendered = metrics.render();
+        let lines_mentioning_weird: Vec<&str> = rendered
+            .lines()
+            .filter(|line| line.contains("weird"))
+            .collect();
+
+        assert_eq!(lines_mentioning_weird.len(), 1);
+        let line = lines_mentioning_weird[0];
+        assert!(line.contains("\\\""));
+        assert!(line.contains("\\\\"));
+        assert!(line.contains("\\n"));
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn pattern_totals_respect_allowlist_when_configured() {
+        let metrics = Metrics::new();
+        let allowed = vec!["absolutely".to_string()];
+
+        metrics.record_pattern_totals(
+            &HashMap::from([("absolutely".to_string(), 3), ("not-allowed".to_string(), 9)]),
+            Some(&allowed),
+        );
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("pattern=\"absolutely\"} 3"));
+        assert!(!rendered.contains("not-allowed"));
+    }
+
+    #[test]
+    fn pattern_totals_are_capped_without_an_allowlist() {
+        let metrics = Metrics::new();
+
+        for i in 0..MAX_TRACKED_PATTERNS + 10 {
+            metrics.record_pattern_totals(&HashMap::from([(format!("pattern-{i}"), 1)]), None);
+        }
+
+        let distinct = metrics
+            .render()
+            .lines()
+            .filter(|line| line.starts_with("absolutelyright_pattern_total{"))
+            .count();
+        assert_eq!(distinct, MAX_TRACKED_PATTERNS);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use tokio_rusqlite::Connection;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DayCount {
+    pub day: String,
+    #[serde(flatten)]
+    pub patterns: HashMap<String, u32>,
+    pub total_messages: u32,
+}
+
+/// Storage backend for day counts. Implementations must be safe to share
+/// behind an `Arc` and called concurrently from many request handlers.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn today(&self) -> anyhow::Result<HashMap<String, u32>>;
+    async fn history(&self) -> anyhow::Result<Vec<DayCount>>;
+    /// Overwrites a day's patterns and total wholesale. Prefer `increment`
+    /// for live tallying, since two concurrent `set` calls clobber each
+    /// other's counts.
+    async fn set(
+        &self,
+        day: String,
+        patterns: HashMap<String, u32>,
+        total_messages: u32,
+    ) -> anyhow::Result<()>;
+    /// Atomically bumps a single pattern (and the total) for a day, so
+    /// concurrent reporters don't lose updates to a racing read-modify-write.
+    async fn increment(
+        &self,
+        day: String,
+        pattern: String,
+        by: u32,
+        total_messages_delta: u32,
+    ) -> anyhow::Result<()>;
+}
+
+/// Builds a quoted single-level SQLite JSON path for `key`, escaping `\`
+/// and `"` so the key is treated as one flat segment even if it contains
+/// characters (like `.`) that would otherwise be parsed as path syntax.
+fn json_object_path(key: &str) -> String {
+    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("$.\"{escaped}\"")
+}
+
+/// SQLite-backed store, suitable for a single-file deployment.
+pub struct SqliteStore {
+    db: Connection,
+}
+
+impl SqliteStore {
+    pub async fn open(db_path: &str) -> anyhow::Result<Self> {
+        let db = Connection::open(db_path).await?;
+
+        db.call(|conn| {
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS day_counts (
+                    day TEXT PRIMARY KEY,
+                    patterns TEXT NOT NULL DEFAULT '{}',
+                    total_messages INTEGER DEFAULT 0
+                )",
+                [],
+            )?;
+
+            // Migration: Add patterns column if it doesn't exist
+            let has_patterns = conn
+                .prepare("SELECT patterns FROM day_counts LIMIT 1")
+                .is_ok();
+
+            if !has_patterns {
+                // Old schema - migrate data
+                println!("Migrating to new schema with dynamic patterns...");
+                let _ = conn.execute(
+                    "ALTER TABLE day_counts ADD COLUMN patterns TEXT DEFAULT '{}'",
+                    [],
+                );
+
+                // Migrate existing count and right_count to JSON
+                conn.execute(
+                    r#"UPDATE day_counts
+                       SET patterns = json_object(
+                           'absolutely', COALESCE(count, 0),
+                           'right', COALESCE(right_count, 0)
+                       )"#,
+                    [],
+                )?;
+
+                println!("Migration complete!");
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        Ok(Self { db })
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn today(&self) -> anyhow::Result<HashMap<String, u32>> {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        let (patterns_json, total_messages) = self
+            .db
+            .call(move |conn| {
+                let mut stmt = conn
+                    .prepare("SELECT patterns, total_messages FROM day_counts WHERE day = ?1")?;
+                let result = stmt
+                    .query_row([&today], |row| {
+                        Ok((
+                            row.get::<_, String>(0).unwrap_or_else(|_| "{}".to_string()),
+                            row.get::<_, u32>(1).unwrap_or(0),
+                        ))
+                    })
+                    .unwrap_or(("{}".to_string(), 0));
+                Ok(result)
+            })
+            .await?;
+
+        let mut map: HashMap<String, u32> = serde_json::from_str(&patterns_json)?;
+        map.insert("total_messages".to_string(), total_messages);
+        Ok(map)
+    }
+
+    async fn history(&self) -> anyhow::Result<Vec<DayCount>> {
+        let history = self
+            .db
+            .call(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT day, patterns, total_messages FROM day_counts ORDER BY day",
+                )?;
+                let days = stmt
+                    .query_map([], |row| {
+                        let day: String = row.get(0)?;
+                        let patterns_json: String =
+                            row.get::<_, String>(1).unwrap_or_else(|_| "{}".to_string());
+                        let total_messages: u32 = row.get(2).unwrap_or(0);
+
+                        let patterns: HashMap<String, u32> =
+                            serde_json::from_str(&patterns_json).unwrap_or_default();
+
+                        Ok(DayCount {
+                            day,
+                            patterns,
+                            total_messages,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(days)
+            })
+            .await?;
+
+        Ok(history)
+    }
+
+    async fn set(
+        &self,
+        day: String,
+        patterns: HashMap<String, u32>,
+        total_messages: u32,
+    ) -> anyhow::Result<()> {
+        let patterns_json = serde_json::to_string(&patterns)?;
+
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO day_counts (day, patterns, total_messages) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(day) DO UPDATE SET patterns = ?2, total_messages = ?3",
+                    [&day, &patterns_json, &total_messages.to_string()],
+                )?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn increment(
+        &self,
+        day: String,
+        pattern: String,
+        by: u32,
+        total_messages_delta: u32,
+    ) -> anyhow::Result<()> {
+        // Build the JSON path ourselves (quoted, with `"` and `\` escaped)
+        // instead of `'$.' || pattern` in SQL: a pattern containing `.`
+        // would otherwise be parsed as a path separator and nest the value
+        // instead of storing it under the flat key the caller asked for.
+        let path = json_object_path(&pattern);
+
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO day_counts (day, patterns, total_messages)
+                     VALUES (?1, json_object(?2, ?3), ?4)
+                     ON CONFLICT(day) DO UPDATE SET
+                         patterns = json_set(
+                             patterns,
+                             ?5,
+                             COALESCE(json_extract(patterns, ?5), 0) + ?3
+                         ),
+                         total_messages = total_messages + ?4",
+                    params![day, pattern, by, total_messages_delta, path],
+                )?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// `sqlx`-backed store for deployments that outgrow a single-file SQLite DB.
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(postgres_uri: &str) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(postgres_uri)
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS day_counts (
+                day TEXT PRIMARY KEY,
+                patterns JSONB NOT NULL DEFAULT '{}',
+                total_messages INTEGER NOT NULL DEFAULT 0
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn today(&self) -> anyhow::Result<HashMap<String, u32>> {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        let row = sqlx::query("SELECT patterns, total_messages FROM day_counts WHERE day = $1")
+            .bind(&today)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let (patterns_json, total_messages): (serde_json::Value, i32) = match row {
+            Some(row) => (row.try_get("patterns")?, row.try_get("total_messages")?),
+            None => (serde_json::json!({}), 0),
+        };
+
+        let mut map: HashMap<String, u32> = serde_json::from_value(patterns_json)?;
+        map.insert("total_messages".to_string(), total_messages as u32);
+        Ok(map)
+    }
+
+    async fn history(&self) -> anyhow::Result<Vec<DayCount>> {
+        let rows = sqlx::query("SELECT day, patterns, total_messages FROM day_counts ORDER BY day")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let day: String = row.try_get("day")?;
+                let patterns_json: serde_json::Value = row.try_get("patterns")?;
+                let total_messages: i32 = row.try_get("total_messages")?;
+                let patterns: HashMap<String, u32> = serde_json::from_value(patterns_json)?;
+
+                Ok(DayCount {
+                    day,
+                    patterns,
+                    total_messages: total_messages as u32,
+                })
+            })
+            .collect()
+    }
+
+    async fn set(
+        &self,
+        day: String,
+        patterns: HashMap<String, u32>,
+        total_messages: u32,
+    ) -> anyhow::Result<()> {
+        let patterns_json = serde_json::to_value(&patterns)?;
+
+        sqlx::query(
+            "INSERT INTO day_counts (day, patterns, total_messages) VALUES ($1, $2, $3)
+             ON CONFLICT (day) DO UPDATE SET patterns = $2, total_messages = $3",
+        )
+        .bind(&day)
+        .bind(&patterns_json)
+        .bind(total_messages as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn increment(
+        &self,
+        day: String,
+        pattern: String,
+        by: u32,
+        total_messages_delta: u32,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO day_counts (day, patterns, total_messages)
+             VALUES ($1, jsonb_build_object($2, $3::bigint), $4)
+             ON CONFLICT (day) DO UPDATE SET
+                 patterns = jsonb_set(
+                     day_counts.patterns,
+                     array[$2],
+                     to_jsonb(COALESCE((day_counts.patterns->>$2)::bigint, 0) + $3::bigint)
+                 ),
+                 total_messages = day_counts.total_messages + $4",
+        )
+        .bind(&day)
+        .bind(&pattern)
+        .bind(by as i64)
+        .bind(total_messages_delta as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn temp_store() -> SqliteStore {
+        let path = std::env::temp_dir().join(format!(
+            "absolutelyright-store-test-{}.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        SqliteStore::open(path.to_str().unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn increment_accumulates_and_stays_readable() {
+        let store = temp_store().await;
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        store
+            .increment(today.clone(), "absolutely".to_string(), 3, 1)
+            .await
+            .unwrap();
+        store
+            .increment(today.clone(), "absolutely".to_string(), 4, 1)
+            .await
+            .unwrap();
+
+        let map = store.today().await.unwrap();
+        assert_eq!(map.get("absolutely"), Some(&7));
+        assert_eq!(map.get("total_messages"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn increment_with_dotted_pattern_name_stays_flat() {
+        let store = temp_store().await;
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        // A pattern containing "." must not be treated as a JSON path
+        // separator: it should stay a single flat key, and `today()` must
+        // still be able to deserialize the result as `HashMap<String, u32>`.
+        store
+            .increment(today, "a.b".to_string(), 5, 0)
+            .await
+            .unwrap();
+
+        let map = store.today().await.unwrap();
+        assert_eq!(map.get("a.b"), Some(&5));
+    }
+}